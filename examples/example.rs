@@ -20,20 +20,89 @@ pub fn add_two_numbers(a: i32, b: i32) -> i32 {
     a + b
 }
 
-pub fn main() {
+/// This function uses `marshal` so the generated native entry point accepts/returns raw JNI
+/// types (`jstring`) while `greet` itself keeps using idiomatic Rust types
+#[mangle(package = "com.example", class = "Example", marshal = true)]
+pub fn greet(name: String) -> String {
+    format!("Hello, {}!", name)
+}
 
+/// This function uses `exception` so a `Err` return is thrown into Java as a
+/// `java.lang.ArithmeticException` instead of unwinding across the FFI boundary
+#[mangle(
+    package = "com.example",
+    class = "Example",
+    marshal = true,
+    exception = "java.lang.ArithmeticException"
+)]
+pub fn divide(a: i32, b: i32) -> Result<i32, String> {
+    if b == 0 {
+        Err("division by zero".to_string())
+    } else {
+        Ok(a / b)
+    }
+}
+
+/// These two functions are both exposed to Java as overloads of `process`; `overload` derives
+/// their disambiguating `__<descriptor>` suffixes from the Rust parameter types instead of
+/// requiring them to be written out by hand
+#[mangle(package = "com.example", class = "Example", method = "process", overload = true)]
+pub fn process_int(value: i32) -> i32 {
+    value * 2
+}
+
+#[mangle(package = "com.example", class = "Example", method = "process", overload = true)]
+pub fn process_string(value: String) -> String {
+    value.repeat(2)
+}
+
+/// A Rust type backing a Java object, constructed by boxing an instance and stashing the raw
+/// pointer in the Java object's `nativeHandle` field. `increment` uses `handle` so `#[mangle]`
+/// can recover `self` from that field before dispatching to it.
+pub struct Counter {
+    count: i32,
+}
+
+impl Counter {
+    #[mangle(package = "com.example", class = "Counter", handle = "nativeHandle")]
+    pub fn increment(&mut self, by: i32) -> i32 {
+        self.count += by;
+        self.count
+    }
+}
+
+// Note: every `Java_*` entry point generated above additionally takes `JNIEnv` and either `JClass` or
+// (for `increment`) `JObject` as its first two parameters (like any real native JNI function),
+// so none of them can be called directly from a plain `fn main()` without a running JVM to hand
+// them over. The functions below are instead called through their original Rust names, which is
+// all that `main` needs.
+
+pub fn main() {
     // my_rust_function is available as both a rust function and a java one
-    Java_com_example_Example_my_1rust_1function("Called using mangled name");
     my_rust_function("Called using rust name");
 
-    // While `function_for_java` is only available as a java function, 
-    // it is technically still possible to call from Rust too
-    Java_com_example_Example_function_1for_1java();
+    // `function_for_java` has `alias=false`, so it's no longer reachable from Rust by its
+    // original name outside of this module; only `Java_com_example_Example_function_1for_1java`
+    // is exposed
 
     // Again, `add_two_numbers` may be called both ways (since aliases are enabled by default)
-    let nums = Java_com_example_Example_addTwoNumbers(1, 2);
-    println!("add_two_numbers (Java name) = {}", nums);
     let nums = add_two_numbers(1, 2);
     println!("add_two_numbers (Rust name) = {}", nums);
 
+    // greet is still callable from Rust with its native types; the marshalled
+    // Java_com_example_Example_greet entry point needs a real JNIEnv, so it can
+    // only be called from the JVM
+    println!("greet = {}", greet("world".to_string()));
+
+    // divide is likewise still callable from Rust with its native `Result`; only the
+    // generated Java_com_example_Example_divide entry point throws the exception
+    println!("divide = {:?}", divide(10, 2));
+
+    println!("process_int = {}", process_int(21));
+    println!("process_string = {}", process_string("ab".to_string()));
+
+    // `increment` is likewise still callable from Rust on a plain `Counter`; only the generated
+    // Java_com_example_Counter_increment entry point recovers `self` from a `nativeHandle` field
+    let mut counter = Counter { count: 0 };
+    println!("counter.increment(5) = {}", counter.increment(5));
 }
@@ -0,0 +1,146 @@
+//! Automatic conversion between native Rust types and their raw JNI representations, used by
+//! the `marshal` option of `jni-mangle`'s `#[mangle]`/`#[mangle_raw]`.
+//!
+//! This is a plain support crate (not a proc-macro crate) so that `FromJava`/`IntoJava`/
+//! `JavaClass` can be exported as ordinary items: generated entry points reference them as
+//! `::jni_mangle_marshal::FromJava`/`IntoJava`, so any crate using `marshal = true` needs this
+//! crate as a dependency alongside `jni-mangle` itself.
+#![allow(unsafe_code)]
+
+use jni::{
+    errors::Result,
+    objects::{JObject, JString},
+    sys::{jboolean, jbyte, jchar, jdouble, jfloat, jint, jlong, jobjectArray, jshort, jstring},
+    JNIEnv,
+};
+
+/// Converts a raw JNI value into its native Rust representation.
+pub trait FromJava<'local>: Sized {
+    /// The raw JNI type this is converted from.
+    type From;
+
+    /// Converts `value` into `Self`, using `env` for any JVM calls the conversion needs.
+    fn from_java(value: Self::From, env: &mut JNIEnv<'local>) -> Result<Self>;
+}
+
+/// Converts a native Rust value into its raw JNI representation.
+pub trait IntoJava<'local> {
+    /// The raw JNI type this is converted into.
+    type Into;
+
+    /// Converts `self` into its JNI representation, using `env` for any JVM calls the conversion needs.
+    fn into_java(self, env: &mut JNIEnv<'local>) -> Result<Self::Into>;
+}
+
+/// Associates a Rust type with the fully-qualified (slash-form) JNI class name of its
+/// Java representation. Used when building object arrays, since the JVM needs to know
+/// the element class up front.
+pub trait JavaClass {
+    /// The JNI internal (slash-form) class name, e.g. `"java/lang/String"`.
+    const CLASS: &'static str;
+}
+
+/// Implements `FromJava`/`IntoJava` for a primitive that maps losslessly to a JNI primitive.
+macro_rules! impl_primitive {
+    ($rust_ty:ty, $jni_ty:ty) => {
+        impl<'local> FromJava<'local> for $rust_ty {
+            type From = $jni_ty;
+
+            fn from_java(value: Self::From, _env: &mut JNIEnv<'local>) -> Result<Self> {
+                Ok(value as $rust_ty)
+            }
+        }
+
+        impl<'local> IntoJava<'local> for $rust_ty {
+            type Into = $jni_ty;
+
+            fn into_java(self, _env: &mut JNIEnv<'local>) -> Result<Self::Into> {
+                Ok(self as $jni_ty)
+            }
+        }
+    };
+}
+
+impl_primitive!(i8, jbyte);
+impl_primitive!(i16, jshort);
+// `u16` maps to the JNI `char` descriptor the same way `char` does (both are UTF-16 code units),
+// so `overload`'s descriptor mapper and `marshal` need to agree on `u16` being supported.
+impl_primitive!(u16, jchar);
+impl_primitive!(i32, jint);
+impl_primitive!(i64, jlong);
+impl_primitive!(f32, jfloat);
+impl_primitive!(f64, jdouble);
+
+impl<'local> FromJava<'local> for bool {
+    type From = jboolean;
+
+    fn from_java(value: Self::From, _env: &mut JNIEnv<'local>) -> Result<Self> {
+        Ok(value != 0)
+    }
+}
+
+impl<'local> IntoJava<'local> for bool {
+    type Into = jboolean;
+
+    fn into_java(self, _env: &mut JNIEnv<'local>) -> Result<Self::Into> {
+        Ok(self as jboolean)
+    }
+}
+
+impl<'local> FromJava<'local> for char {
+    type From = jchar;
+
+    fn from_java(value: Self::From, _env: &mut JNIEnv<'local>) -> Result<Self> {
+        Ok(char::from_u32(value as u32).unwrap_or_default())
+    }
+}
+
+impl<'local> IntoJava<'local> for char {
+    type Into = jchar;
+
+    fn into_java(self, _env: &mut JNIEnv<'local>) -> Result<Self::Into> {
+        Ok(self as jchar)
+    }
+}
+
+impl<'local> FromJava<'local> for String {
+    type From = jstring;
+
+    // SAFETY: `value` is a `jstring` handed to us by the JVM as the argument of a `#[mangle]`
+    // generated entry point, so it's always either null or a valid reference for 'local.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn from_java(value: Self::From, env: &mut JNIEnv<'local>) -> Result<Self> {
+        let value = unsafe { JString::from_raw(value) };
+        env.get_string(&value).map(Into::into)
+    }
+}
+
+impl<'local> IntoJava<'local> for String {
+    type Into = jstring;
+
+    fn into_java(self, env: &mut JNIEnv<'local>) -> Result<Self::Into> {
+        Ok(env.new_string(self)?.into_raw())
+    }
+}
+
+impl JavaClass for String {
+    const CLASS: &'static str = "java/lang/String";
+}
+
+impl<'local, T> IntoJava<'local> for Vec<T>
+where
+    T: IntoJava<'local, Into = jni::sys::jobject> + JavaClass,
+{
+    type Into = jobjectArray;
+
+    fn into_java(self, env: &mut JNIEnv<'local>) -> Result<Self::Into> {
+        let class = env.find_class(T::CLASS)?;
+        let array = env.new_object_array(self.len() as i32, class, JObject::null())?;
+        for (index, item) in self.into_iter().enumerate() {
+            let element = item.into_java(env)?;
+            let element = unsafe { JObject::from_raw(element) };
+            env.set_object_array_element(&array, index as i32, element)?;
+        }
+        Ok(array.into_raw())
+    }
+}
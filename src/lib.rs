@@ -4,16 +4,23 @@
 use args::{parse_macro_args, TOrTokens};
 use darling::{FromMeta, ToTokens};
 use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::Block;
 use utils::{
+    class_name::to_internal_form,
+    descriptor::{last_type_ident, type_descriptor},
     escape::escape_string,
     validators::{is_valid_class, is_valid_method, is_valid_package},
 };
 mod args;
 mod utils;
 
+// Note: this crate is `proc-macro = true`, so it can only export `#[proc_macro_attribute]`
+// functions — the `FromJava`/`IntoJava`/`JavaClass` traits used by `marshal` live in the
+// ordinary `jni-mangle-marshal` support crate instead, which generated entry points reference
+// as `::jni_mangle_marshal::*`. Crates using `marshal = true` need that crate as a dependency
+// alongside this one.
+
 /// Arguments accepted by the `#[mangle]` macro
 #[derive(Debug, FromMeta)]
 struct MangleArgs {
@@ -25,8 +32,19 @@ struct MangleArgs {
     method: Option<String>,
     /// Optional Java args (used to disambiguate overloaded functions)
     args: Option<String>,
+    /// Whether to derive the overload-disambiguation suffix from the Rust function's parameter
+    /// types instead of requiring `args` to be written out by hand
+    overload: Option<bool>,
     /// Whether to alias the function with the original name
     alias: Option<bool>,
+    /// Whether to automatically marshal JNI types to/from the function's native Rust types
+    marshal: Option<bool>,
+    /// The fully-qualified Java exception class to throw when the function returns `Err`
+    /// (e.g. `"java.lang.RuntimeException"`)
+    exception: Option<String>,
+    /// The `jlong` field (on the Java object backing `self`) that stores the native instance's
+    /// pointer; required for methods with a `self` receiver
+    handle: Option<String>,
 }
 
 /// Mangle a Rust function to be callable from Java through JNI
@@ -38,26 +56,62 @@ struct MangleArgs {
 /// - `class`: The Java class name this method belongs to
 /// - `method` (optional): The Java method name (defaults to the Rust function name)
 /// - `args` (optional): The Java method args (used to disambiguate overloaded functions)
+/// - `overload` (optional): Whether to derive `args` automatically from the Rust function's
+///   parameter types instead of writing out the JNI descriptor by hand (defaults to `false`)
 /// - `alias` (optional): Whether to alias the function with the original name (defaults to `true`)
+/// - `marshal` (optional): Whether to automatically convert arguments/return values between their
+///   native Rust types and their raw JNI representations (defaults to `false`)
+/// - `handle` (optional): The `jlong` field, on the Java object backing `self`, that stores the
+///   native instance's pointer; required when mangling a method with a `self` receiver
 ///
 /// Aliasing allows the function to be called from Rust using its original name as well as from Java using
 /// the mangled name. If Aliasing is disabled, the rust function name will not be callable from Rust.
 ///
+/// When `marshal` is enabled, the function's parameters and return type are converted through the
+/// `FromJava`/`IntoJava` traits from the `jni-mangle-marshal` crate, so a function like
+/// `fn greet(name: String) -> String` can be called directly from Java using `jstring` in and
+/// out. The original function keeps its native signature (only its visibility depends on
+/// `alias`), so wherever it's callable from Rust it takes and returns its native types. Crates using `marshal = true` need `jni-mangle-marshal`
+/// as a dependency alongside `jni-mangle`.
+///
+/// The generated native entry point always receives the standard `JNIEnv`/`JClass` parameters every
+/// JNI function is called with, regardless of whether the Rust function declares them. If the
+/// function's own signature starts with a `&mut JNIEnv`/`JClass` parameter (in that order) they're
+/// forwarded to it; otherwise they're simply dropped.
+///
+/// - `exception` (optional): A fully-qualified Java exception class (e.g.
+///   `"java.lang.RuntimeException"`) to throw when the function returns `Err`
+///
+/// `exception` requires the function to return a `Result<T, E>` where `E: Display`. On `Err`, the
+/// generated entry point calls `env.throw_new` with the exception class and the error's `Display`
+/// output, then returns a default/zeroed value instead of unwinding across the FFI boundary. On
+/// `Ok(v)`, `v` is returned (marshalled as usual when `marshal` is enabled).
+///
+/// `overload` computes the `args` suffix from the Rust function's parameter types (skipping any
+/// leading `JNIEnv`/`JClass` parameter) instead of requiring it to be written out as a raw JNI
+/// descriptor string. `args` and `overload` are mutually exclusive.
+///
+/// `handle` lets `#[mangle]` be used on a method (`&self`/`&mut self`) inside an `impl` block. The
+/// generated entry point takes `this: JObject` instead of `class: JClass`, reads `handle` off of
+/// it as a `jlong`, and recovers `self` from that pointer before dispatching to the method. This
+/// assumes the Java object's `handle` field was set up to point at a boxed instance of the Rust
+/// type (e.g. via `Box::into_raw` when the object was constructed).
+///
 /// ## Example
 /// ```
 /// use jni_mangle::mangle;
 ///
 /// #[mangle(package="com.example", class="Example", method="addTwoNumbers")]
 /// pub fn add_two_numbers(a: i32, b: i32) -> i32 {
-///    a + b    
+///    a + b
 /// }
 ///
-/// // This function is callable from rust using both the mangled name and
-/// // the original name since `alias` is enabled by default
-/// assert_eq!(
-///     add_two_numbers(1, 2),
-///     Java_com_example_Example_addTwoNumbers(1, 2)
-/// );
+/// // This function is still callable from rust using its original name since
+/// // `alias` is enabled by default. `Java_com_example_Example_addTwoNumbers` is
+/// // also generated, but (like every native JNI entry point) it additionally takes
+/// // `JNIEnv`/`JClass` as its first two parameters, so it can only be called from a
+/// // running JVM.
+/// assert_eq!(add_two_numbers(1, 2), 3);
 /// ```
 #[proc_macro_attribute]
 pub fn mangle(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -88,6 +142,69 @@ pub fn mangle(args: TokenStream, input: TokenStream) -> TokenStream {
             .to_compile_error()
             .into();
     }
+    if let Some(exception) = &args.exception {
+        if !is_valid_package(exception) {
+            return syn::Error::new_spanned(exception, "Invalid Java exception class name")
+                .to_compile_error()
+                .into();
+        }
+    }
+    if args.args.is_some() && args.overload.unwrap_or(false) {
+        return syn::Error::new(
+            input.sig.ident.span(),
+            "Cannot use both `args` and `overload`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    // If `overload` is enabled, derive the args descriptor from the function's own parameter
+    // types instead of requiring it to be written out by hand
+    let overload_args = if args.overload.unwrap_or(false) {
+        let arg_start = match input.sig.inputs.first() {
+            Some(syn::FnArg::Receiver(_)) => 1,
+            _ => 0,
+        };
+        let wants_env = wants_leading_param(&input.sig.inputs, arg_start, "JNIEnv", true);
+        // Like `expand`, only a free function/static method can have a leading `JClass` forwarded
+        let wants_class = arg_start == 0
+            && wants_leading_param(
+                &input.sig.inputs,
+                arg_start + wants_env as usize,
+                "JClass",
+                false,
+            );
+        let skip = arg_start + wants_env as usize + wants_class as usize;
+
+        let mut descriptor = String::new();
+        for arg in input.sig.inputs.iter().skip(skip) {
+            let ty = match arg {
+                syn::FnArg::Typed(pat_type) => &pat_type.ty,
+                syn::FnArg::Receiver(_) => {
+                    return syn::Error::new_spanned(
+                        arg,
+                        "Cannot mangle a method with more than one receiver",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+            };
+            match type_descriptor(ty) {
+                Some(field_descriptor) => descriptor.push_str(&field_descriptor),
+                None => {
+                    return syn::Error::new_spanned(
+                        ty,
+                        "Cannot derive a JNI overload descriptor for this type",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+            }
+        }
+        Some(descriptor)
+    } else {
+        args.args
+    };
 
     // Build the mangled function name
     let mut mangled_fn_name = format!(
@@ -98,16 +215,22 @@ pub fn mangle(args: TokenStream, input: TokenStream) -> TokenStream {
     );
 
     // If we have args, append them to the mangled name
-    if args.args.is_some() {
-        mangled_fn_name.push_str(&format!("__{}", escape_string(&args.args.unwrap())));
+    if let Some(overload_args) = overload_args {
+        mangled_fn_name.push_str(&format!("__{}", escape_string(&overload_args)));
     }
 
     // Hand off to the raw mangle macro for the main processing logic
     let should_alias = args.alias.unwrap_or(true);
-    mangle_raw(
-        quote! {name=#mangled_fn_name, alias=#should_alias}.into(),
-        input.into_token_stream().into(),
-    )
+    let should_marshal = args.marshal.unwrap_or(false);
+    let mut raw_args = quote! {name=#mangled_fn_name, alias=#should_alias, marshal=#should_marshal};
+    if let Some(exception) = args.exception {
+        let exception = to_internal_form(&exception);
+        raw_args.extend(quote! { , exception=#exception });
+    }
+    if let Some(handle) = args.handle {
+        raw_args.extend(quote! { , handle=#handle });
+    }
+    mangle_raw(raw_args.into(), input.into_token_stream().into())
 }
 
 /// Arguments accepted by the `#[mangle_raw]` macro
@@ -117,6 +240,14 @@ struct MangleRawArgs {
     name: String,
     /// Whether to alias the function with the original name
     alias: bool,
+    /// Whether to automatically marshal JNI types to/from the function's native Rust types
+    marshal: bool,
+    /// The Java exception class (already in internal/slash form) to throw when the function
+    /// returns `Err`
+    exception: Option<String>,
+    /// The `jlong` field (on the Java object backing `self`) that stores the native instance's
+    /// pointer; required for methods with a `self` receiver
+    handle: Option<String>,
 }
 
 /// # Warning: You probably don't want to use this unless you know what you're doing
@@ -125,22 +256,28 @@ struct MangleRawArgs {
 /// ## Macro arguments
 /// - `name`: The name to mangle the function to
 /// - `alias`: Whether to alias the function with the original name
+/// - `marshal`: Whether to automatically convert arguments/return values between their native
+///   Rust types and their raw JNI representations
+/// - `exception` (optional): The Java exception class, already in internal/slash form (e.g.
+///   `"java/lang/RuntimeException"`), to throw when the function returns `Err`
+/// - `handle` (optional): The `jlong` field storing the native instance pointer; required for
+///   methods with a `self` receiver
 ///
 /// ## Example
 /// ```
 /// use jni_mangle::mangle_raw;
 ///
-/// #[mangle_raw(name="Java_com_example_Example_addTwoNumbers", alias=true)]
+/// #[mangle_raw(name="Java_com_example_Example_addTwoNumbers", alias=true, marshal=false)]
 /// pub fn add_two_numbers(a: i32, b: i32) -> i32 {
 ///   a + b
 /// }
 ///
-/// // This function is callable from rust using both the mangled name and
-/// // the original name since `alias` is enabled by default
-/// assert_eq!(
-///    add_two_numbers(1, 2),   
-///    Java_com_example_Example_addTwoNumbers(1, 2)
-/// );
+/// // This function is still callable from rust using its original name since
+/// // `alias=true` is set. `Java_com_example_Example_addTwoNumbers` is
+/// // also generated, but (like every native JNI entry point) it additionally takes
+/// // `JNIEnv`/`JClass` as its first two parameters, so it can only be called from a
+/// // running JVM.
+/// assert_eq!(add_two_numbers(1, 2), 3);
 /// ```
 #[proc_macro_attribute]
 pub fn mangle_raw(args: TokenStream, input: TokenStream) -> TokenStream {
@@ -152,58 +289,291 @@ pub fn mangle_raw(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Parse the function
     let input_fn = syn::parse_macro_input!(input as syn::ItemFn);
-    let mut output_fn = input_fn.clone();
 
-    // Rename the function
-    let rust_name_ident = output_fn.sig.ident.clone();
-    output_fn.sig.ident = syn::Ident::new(&args.name, output_fn.sig.ident.span());
+    expand(
+        &args.name,
+        args.alias,
+        args.marshal,
+        args.exception,
+        args.handle,
+        input_fn,
+    )
+}
 
-    // Set the function to be `extern "system"`
-    output_fn.sig.abi = Some(syn::parse_quote! { extern "system" });
+/// If `ty` is `Result<T, E>`, returns `T`.
+fn result_ok_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(generics) = &segment.arguments else {
+        return None;
+    };
+    match generics.args.first()? {
+        syn::GenericArgument::Type(ok_ty) => Some(ok_ty),
+        _ => None,
+    }
+}
 
-    // Wrap the function in needed attributes
-    let mut output = quote! {
-        #[no_mangle]
-        #[allow(non_snake_case)]
-        #output_fn
+/// Returns `true` if the parameter at `index` is typed as `ident` (e.g. `"JNIEnv"`/`"JClass"`) in
+/// exactly the shape the generated entry point forwards it as: a `&mut` reference when
+/// `require_mut_ref` is set (matching the `&mut env` call argument), or a bare owned value
+/// otherwise (matching the `class`/`this` call argument). Any other shape — an owned `JNIEnv`, an
+/// `&JNIEnv`, a `&JClass`, etc. — is left alone rather than misdetected, so it's treated as a
+/// regular parameter instead of silently generating a call that fails to type-check.
+fn wants_leading_param(
+    inputs: &syn::punctuated::Punctuated<syn::FnArg, syn::token::Comma>,
+    index: usize,
+    ident: &str,
+    require_mut_ref: bool,
+) -> bool {
+    let Some(syn::FnArg::Typed(pat_type)) = inputs.iter().nth(index) else {
+        return false;
     };
+    let ty = &*pat_type.ty;
+    match ty {
+        syn::Type::Reference(type_ref) if require_mut_ref && type_ref.mutability.is_some() => {
+            last_type_ident(ty).as_deref() == Some(ident)
+        }
+        syn::Type::Reference(_) => false,
+        _ if require_mut_ref => false,
+        _ => last_type_ident(ty).as_deref() == Some(ident),
+    }
+}
 
-    // If aliasing is enabled, add another function with the original name and args
-    if args.alias {
-        // Clone the input function again to modify into the aliased function. 
-        // The reason for doing this is to avoid needing to copy over every generic, 
-        // docstring, modifier, where clause, etc...
-        let mut alias_fn = input_fn.clone();
-
-        // Build a list of tokens to be the arguments for the inner function
-        let inner_fn_args_list = alias_fn
-            .sig
-            .inputs
-            .iter()
-            .map(|arg| match arg {
-                syn::FnArg::Receiver(_) => panic!("Cannot alias a method with a receiver"),
-                syn::FnArg::Typed(pat_type) => pat_type.pat.clone(),
-            })
-            .map(|pat| quote! { #pat })
-            .collect::<Vec<TokenStream2>>();
-
-        // Replace the name with the original name again
-        alias_fn.sig.ident = rust_name_ident.clone();
-
-        // Replace the body with a function call
-        alias_fn.block = Box::new(syn::parse_quote! {
-            {
-                #rust_name_ident (#(#inner_fn_args_list),*)
+/// Builds the `extern "system"` entry point (and, if `alias` is enabled, keeps the original
+/// function callable from Rust under its own name).
+///
+/// The generated entry point always receives `JNIEnv` and either `JClass` (free functions/static
+/// methods) or `JObject` (methods with a receiver) as its first two parameters, the way every
+/// real JNI function is called. If the user's own function wants the env/class (detected via a
+/// leading `&mut JNIEnv`/`JClass` parameter, in that order) they're forwarded to it; otherwise
+/// they're dropped. The remaining parameters and the return value are forwarded as-is, or
+/// marshalled through `jni-mangle-marshal`'s `FromJava`/`IntoJava` when `marshal` is enabled.
+///
+/// If `exception` is set, the function must return a `Result<T, E>` (`E: Display`): on `Err` the
+/// entry point throws `exception` with the error's `Display` output and returns a default value,
+/// and on `Ok(v)` it returns `v` (marshalled as usual).
+///
+/// If the function takes `&self`/`&mut self`, `handle` must name the `jlong` field (on the Java
+/// object backing `self`) that stores the boxed Rust instance's pointer; it's read out of `this`
+/// and used to recover `self` before dispatching to the method.
+fn expand(
+    mangled_name: &str,
+    alias: bool,
+    marshal: bool,
+    exception: Option<String>,
+    handle: Option<String>,
+    input_fn: syn::ItemFn,
+) -> TokenStream {
+    let rust_name_ident = input_fn.sig.ident.clone();
+    let mangled_name_ident = syn::Ident::new(mangled_name, rust_name_ident.span());
+
+    let receiver = match input_fn.sig.inputs.first() {
+        Some(syn::FnArg::Receiver(receiver)) => Some(receiver.clone()),
+        _ => None,
+    };
+
+    if let Some(receiver) = &receiver {
+        if receiver.reference.is_none() {
+            return syn::Error::new_spanned(
+                receiver,
+                "`#[mangle]` only supports `&self`/`&mut self` receivers",
+            )
+            .to_compile_error()
+            .into();
+        }
+    }
+
+    let handle_field = match (&receiver, &handle) {
+        (Some(_), Some(handle)) => Some(handle.clone()),
+        (Some(_), None) => {
+            return syn::Error::new_spanned(
+                &input_fn.sig,
+                "Methods with a receiver require the `handle` option, naming the `jlong` field \
+                 that stores the native pointer",
+            )
+            .to_compile_error()
+            .into();
+        }
+        (None, Some(_)) => {
+            return syn::Error::new_spanned(
+                &input_fn.sig,
+                "`handle` only applies to methods with a `self` receiver",
+            )
+            .to_compile_error()
+            .into();
+        }
+        (None, None) => None,
+    };
+
+    let arg_start = if receiver.is_some() { 1 } else { 0 };
+    let wants_env = wants_leading_param(&input_fn.sig.inputs, arg_start, "JNIEnv", true);
+    // A method with a receiver is always entered with `this: JObject`, never `class: JClass`, so
+    // there's no `class` variable for a leading `JClass` parameter to forward to; only look for
+    // one on free functions/static methods.
+    let wants_class = receiver.is_none()
+        && wants_leading_param(
+            &input_fn.sig.inputs,
+            arg_start + wants_env as usize,
+            "JClass",
+            false,
+        );
+    let skip = arg_start + wants_env as usize + wants_class as usize;
+
+    let mut call_args = Vec::new();
+    if wants_env {
+        call_args.push(quote! { &mut env });
+    }
+    if wants_class {
+        call_args.push(quote! { class });
+    }
+
+    // Build the entry point's own parameter list (and, for `marshal`, the conversions needed
+    // to turn each raw JNI argument back into the type the real function expects) from whatever
+    // parameters are left after the receiver/env/class ones.
+    let mut jni_params = Vec::new();
+    let mut convert_stmts = Vec::new();
+    for arg in input_fn.sig.inputs.iter().skip(skip) {
+        match arg {
+            syn::FnArg::Receiver(_) => panic!("Cannot mangle a method with more than one receiver"),
+            syn::FnArg::Typed(pat_type) => {
+                let pat = &pat_type.pat;
+                let ty = &pat_type.ty;
+                if marshal {
+                    jni_params.push(quote! { #pat: <#ty as ::jni_mangle_marshal::FromJava<'local>>::From });
+                    convert_stmts.push(quote! {
+                        let #pat = <#ty as ::jni_mangle_marshal::FromJava<'local>>::from_java(#pat, &mut env)
+                            .expect("failed to convert argument from Java");
+                    });
+                } else {
+                    jni_params.push(quote! { #pat: #ty });
+                }
+                call_args.push(quote! { #pat });
             }
-        });
+        }
+    }
 
-        // Extend the output with the alias function
-        output.extend(quote! {
-            #[no_mangle]
-            #[allow(non_snake_case)]
-            #alias_fn
-        });
+    // The entry point's second parameter and how the real function is ultimately called: for a
+    // free function/static method it's `class: JClass` and a plain call; for a method with a
+    // receiver it's `this: JObject`, from which `self` is recovered via `handle` before
+    // dispatching through `Self::`.
+    let (second_param_name, second_param_ty, recover_stmt, call) = match &receiver {
+        Some(receiver) => {
+            let handle_field = handle_field.expect("checked above");
+            let instance_expr = if receiver.mutability.is_some() {
+                quote! { &mut *(handle as *mut Self) }
+            } else {
+                quote! { &*(handle as *const Self) }
+            };
+            let recover_stmt = quote! {
+                let handle = env
+                    .get_field(&this, #handle_field, "J")
+                    .and_then(|value| value.j())
+                    .expect("failed to read native handle field");
+                let instance = unsafe { #instance_expr };
+            };
+            (
+                quote! { this },
+                quote! { ::jni::objects::JObject<'local> },
+                recover_stmt,
+                quote! { Self::#rust_name_ident(instance, #(#call_args),*) },
+            )
+        }
+        None => (
+            quote! { class },
+            quote! { ::jni::objects::JClass<'local> },
+            quote! {},
+            quote! { #rust_name_ident(#(#call_args),*) },
+        ),
+    };
+
+    let (return_ty, return_stmt) = match (&input_fn.sig.output, &exception) {
+        (syn::ReturnType::Default, None) => (quote! { () }, quote! { #call; }),
+        (_, Some(exception_class)) => {
+            let return_ty = match &input_fn.sig.output {
+                syn::ReturnType::Type(_, ty) => ty,
+                syn::ReturnType::Default => {
+                    return syn::Error::new_spanned(
+                        &input_fn.sig,
+                        "`exception` requires the function to return a `Result`",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+            };
+            let ok_ty = match result_ok_type(return_ty) {
+                Some(ok_ty) => ok_ty,
+                None => {
+                    return syn::Error::new_spanned(
+                        return_ty,
+                        "`exception` requires the function to return a `Result`",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+            };
+            let (jni_ok_ty, ok_value) = if marshal {
+                (
+                    quote! { <#ok_ty as ::jni_mangle_marshal::IntoJava<'local>>::Into },
+                    quote! {
+                        <#ok_ty as ::jni_mangle_marshal::IntoJava<'local>>::into_java(value, &mut env)
+                            .expect("failed to convert return value to Java")
+                    },
+                )
+            } else {
+                (quote! { #ok_ty }, quote! { value })
+            };
+            (
+                jni_ok_ty,
+                quote! {
+                    match #call {
+                        ::core::result::Result::Ok(value) => #ok_value,
+                        ::core::result::Result::Err(err) => {
+                            env.throw_new(#exception_class, ::std::string::ToString::to_string(&err))
+                                .expect("failed to throw Java exception");
+                            ::core::default::Default::default()
+                        }
+                    }
+                },
+            )
+        }
+        (syn::ReturnType::Type(_, ty), None) if marshal => (
+            quote! { <#ty as ::jni_mangle_marshal::IntoJava<'local>>::Into },
+            quote! {
+                let __result = #call;
+                <#ty as ::jni_mangle_marshal::IntoJava<'local>>::into_java(__result, &mut env)
+                    .expect("failed to convert return value to Java")
+            },
+        ),
+        (syn::ReturnType::Type(_, ty), None) => (quote! { #ty }, quote! { #call }),
+    };
+
+    // Keep the original function around under its own name so the entry point can call into it.
+    // When `alias` is disabled its visibility is dropped, so outside its own module it's no longer
+    // reachable from Rust by that name, only through the generated entry point.
+    let mut inner_fn = input_fn;
+    if !alias {
+        inner_fn.vis = syn::Visibility::Inherited;
     }
 
-    output.into()
+    quote! {
+        #inner_fn
+
+        #[no_mangle]
+        #[allow(non_snake_case, unused_variables, unused_mut)]
+        pub extern "system" fn #mangled_name_ident<'local>(
+            mut env: ::jni::JNIEnv<'local>,
+            #second_param_name: #second_param_ty,
+            #(#jni_params),*
+        ) -> #return_ty {
+            #recover_stmt
+            #(#convert_stmts)*
+            #return_stmt
+        }
+    }
+    .into()
 }
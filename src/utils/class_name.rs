@@ -0,0 +1,22 @@
+//! Utilities for working with Java class names.
+
+/// Converts a dot-separated, fully-qualified Java class name (e.g. `java.lang.RuntimeException`)
+/// into its JNI "internal form", where package separators are slashes (e.g.
+/// `java/lang/RuntimeException`).
+pub fn to_internal_form(class_name: &str) -> String {
+    class_name.replace('.', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_internal_form() {
+        assert_eq!(
+            to_internal_form("java.lang.RuntimeException"),
+            "java/lang/RuntimeException"
+        );
+        assert_eq!(to_internal_form("Example"), "Example");
+    }
+}
@@ -0,0 +1,96 @@
+//! Maps Rust types to JNI field descriptors, used to derive the `__<descriptor>` overload
+//! disambiguation suffix from a function's Rust parameter types.
+
+/// Returns the last path segment of a type, looking through references.
+pub(crate) fn last_type_ident(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last().map(|s| s.ident.to_string()),
+        syn::Type::Reference(type_ref) => last_type_ident(&type_ref.elem),
+        _ => None,
+    }
+}
+
+/// Returns the JNI field descriptor for `ty` (e.g. `i32` -> `I`, `String` -> `Ljava/lang/String;`),
+/// or `None` if `ty` isn't one of the mapped types.
+pub fn type_descriptor(ty: &syn::Type) -> Option<String> {
+    // `&[T]` maps the same way `Vec<T>` does
+    if let syn::Type::Reference(type_ref) = ty {
+        if let syn::Type::Slice(type_slice) = &*type_ref.elem {
+            return Some(format!("[{}", type_descriptor(&type_slice.elem)?));
+        }
+    }
+
+    match last_type_ident(ty)?.as_str() {
+        "bool" => Some("Z".to_string()),
+        "i8" => Some("B".to_string()),
+        "u16" | "char" => Some("C".to_string()),
+        "i16" => Some("S".to_string()),
+        "i32" => Some("I".to_string()),
+        "i64" => Some("J".to_string()),
+        "f32" => Some("F".to_string()),
+        "f64" => Some("D".to_string()),
+        "String" | "str" => Some("Ljava/lang/String;".to_string()),
+        "Vec" => {
+            let syn::Type::Path(type_path) = ty else {
+                return None;
+            };
+            let syn::PathArguments::AngleBracketed(generics) = &type_path.path.segments.last()?.arguments else {
+                return None;
+            };
+            match generics.args.first()? {
+                syn::GenericArgument::Type(element_ty) => {
+                    Some(format!("[{}", type_descriptor(element_ty)?))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_primitive_descriptors() {
+        assert_eq!(type_descriptor(&parse_quote! { bool }).as_deref(), Some("Z"));
+        assert_eq!(type_descriptor(&parse_quote! { i8 }).as_deref(), Some("B"));
+        assert_eq!(type_descriptor(&parse_quote! { u16 }).as_deref(), Some("C"));
+        assert_eq!(type_descriptor(&parse_quote! { char }).as_deref(), Some("C"));
+        assert_eq!(type_descriptor(&parse_quote! { i16 }).as_deref(), Some("S"));
+        assert_eq!(type_descriptor(&parse_quote! { i32 }).as_deref(), Some("I"));
+        assert_eq!(type_descriptor(&parse_quote! { i64 }).as_deref(), Some("J"));
+        assert_eq!(type_descriptor(&parse_quote! { f32 }).as_deref(), Some("F"));
+        assert_eq!(type_descriptor(&parse_quote! { f64 }).as_deref(), Some("D"));
+    }
+
+    #[test]
+    fn test_string_descriptors() {
+        let expected = Some("Ljava/lang/String;".to_string());
+        assert_eq!(type_descriptor(&parse_quote! { String }), expected);
+        assert_eq!(type_descriptor(&parse_quote! { &str }), expected);
+    }
+
+    #[test]
+    fn test_array_descriptors() {
+        assert_eq!(
+            type_descriptor(&parse_quote! { Vec<i32> }).as_deref(),
+            Some("[I")
+        );
+        assert_eq!(
+            type_descriptor(&parse_quote! { &[i32] }).as_deref(),
+            Some("[I")
+        );
+        assert_eq!(
+            type_descriptor(&parse_quote! { Vec<String> }).as_deref(),
+            Some("[Ljava/lang/String;")
+        );
+    }
+
+    #[test]
+    fn test_unsupported_descriptors() {
+        assert_eq!(type_descriptor(&parse_quote! { MyStruct }), None);
+    }
+}
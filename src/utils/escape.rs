@@ -11,6 +11,7 @@ pub fn escape_string(s: &str) -> String {
             ';' => "_2".to_string(),
             '[' => "_3".to_string(),
             '.' => "_".to_string(),
+            '/' => "_".to_string(),
 
             // More complex cases
             _ => {
@@ -41,6 +42,7 @@ mod tests {
         assert_eq!(escape_string("Hello_world"), "Hello_1world");
         assert_eq!(escape_string("Hello;world"), "Hello_2world");
         assert_eq!(escape_string("Hello[world"), "Hello_3world");
+        assert_eq!(escape_string("Ljava/lang/String;"), "Ljava_lang_String_2");
     }
 
     #[test]
@@ -0,0 +1,4 @@
+pub mod class_name;
+pub mod descriptor;
+pub mod escape;
+pub mod validators;